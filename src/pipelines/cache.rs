@@ -0,0 +1,156 @@
+//! A persistent, content-addressed cache used to skip re-copying and re-processing assets that
+//! have not changed since the last build.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::common::path_exists;
+use crate::pipelines::AssetFile;
+
+const CACHE_DIR_NAME: &str = ".trunk-cache";
+const INDEX_FILE_NAME: &str = "index.json";
+
+/// A single cached entry, mapping a source file to the output it last produced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    /// The mtime of the source file as of the last successful run, used as a fast-path check
+    /// before falling back to hashing the full contents.
+    source_mtime: Option<SystemTime>,
+    /// The seahash of the source file's contents as of the last successful run.
+    source_hash: u64,
+    /// The name of the file this source was last copied/compiled to.
+    output_name: String,
+    /// Any additional output files derived from this source (e.g. wasm-bindgen's glue files).
+    derived: Vec<String>,
+    /// Identifies the settings (hashing on/off, tool version, ...) this entry was produced
+    /// under; the entry is treated as a miss if this no longer matches.
+    cache_key: String,
+}
+
+/// A persistent on-disk cache mapping `(source path, content hash)` to the already-produced
+/// output file name, so that unchanged assets can skip being re-read, re-written, and
+/// re-processed by external tools on every build.
+pub struct BuildCache {
+    dir: PathBuf,
+    index: Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+impl BuildCache {
+    /// Load the cache rooted at `.trunk-cache` under `root`, creating it if it does not yet
+    /// exist. A corrupt or missing index is treated as an empty cache rather than an error, so a
+    /// damaged cache never fails a build.
+    pub async fn load(root: &Path) -> Result<Self> {
+        let dir = root.join(CACHE_DIR_NAME);
+        fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("error creating build cache dir {:?}", &dir))?;
+
+        let index_path = dir.join(INDEX_FILE_NAME);
+        let index = if path_exists(&index_path).await? {
+            let raw = fs::read(&index_path)
+                .await
+                .with_context(|| format!("error reading build cache index {:?}", &index_path))?;
+            serde_json::from_slice(&raw).unwrap_or_default()
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            dir,
+            index: Mutex::new(index),
+        })
+    }
+
+    /// Look up the output already produced for `asset` under the given `to_dir`, provided its
+    /// content has not changed and `cache_key` still matches (e.g. hashing is still enabled and
+    /// the responsible tool's version has not changed). Returns `None` on any cache miss.
+    pub async fn lookup(
+        &self,
+        asset: &AssetFile,
+        to_dir: &Path,
+        cache_key: &str,
+    ) -> Result<Option<String>> {
+        let entry = {
+            let index = self.index.lock().unwrap();
+            match index.get(&asset.path) {
+                Some(entry) if entry.cache_key == cache_key => entry.clone(),
+                _ => return Ok(None),
+            }
+        };
+
+        let mtime = source_mtime(asset).await?;
+        let unchanged = match (entry.source_mtime, mtime) {
+            (Some(cached), Some(current)) if cached == current => true,
+            _ => {
+                let bytes = fs::read(&asset.path)
+                    .await
+                    .with_context(|| format!("error reading file for hashing {:?}", &asset.path))?;
+                seahash::hash(&bytes) == entry.source_hash
+            }
+        };
+        if !unchanged {
+            return Ok(None);
+        }
+
+        if !path_exists(&to_dir.join(&entry.output_name)).await? {
+            return Ok(None);
+        }
+        for derived in &entry.derived {
+            if !path_exists(&to_dir.join(derived)).await? {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(entry.output_name))
+    }
+
+    /// Record that `asset` was just processed into `output_name` (plus any `derived` files)
+    /// under `cache_key`, and persist the updated index to disk.
+    pub async fn insert(
+        &self,
+        asset: &AssetFile,
+        output_name: String,
+        derived: Vec<String>,
+        cache_key: String,
+    ) -> Result<()> {
+        let bytes = fs::read(&asset.path)
+            .await
+            .with_context(|| format!("error reading file for hashing {:?}", &asset.path))?;
+        let entry = CacheEntry {
+            source_mtime: source_mtime(asset).await?,
+            source_hash: seahash::hash(&bytes),
+            output_name,
+            derived,
+            cache_key,
+        };
+        self.index.lock().unwrap().insert(asset.path.clone(), entry);
+        self.persist().await
+    }
+
+    /// Write the current index back to disk. Called after every insert so that a killed
+    /// watch-mode build does not lose the progress it already made.
+    async fn persist(&self) -> Result<()> {
+        let raw = {
+            let index = self.index.lock().unwrap();
+            serde_json::to_string(&*index).context("error serializing build cache index")?
+        };
+        let index_path = self.dir.join(INDEX_FILE_NAME);
+        fs::write(&index_path, raw)
+            .await
+            .with_context(|| format!("error writing build cache index {:?}", &index_path))
+    }
+}
+
+async fn source_mtime(asset: &AssetFile) -> Result<Option<SystemTime>> {
+    Ok(fs::metadata(&asset.path)
+        .await
+        .with_context(|| format!("error reading metadata for {:?}", &asset.path))?
+        .modified()
+        .ok())
+}
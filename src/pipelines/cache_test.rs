@@ -0,0 +1,147 @@
+//! Tests for [`super::BuildCache`]'s hit/miss logic.
+
+use std::path::{Path, PathBuf};
+
+use tokio::fs;
+
+use super::BuildCache;
+use crate::pipelines::AssetFile;
+
+/// A fresh scratch directory for one test, cleaned up on drop.
+struct Scratch(PathBuf);
+
+impl Scratch {
+    async fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "trunk-build-cache-test-{name}-{}-{}",
+            std::process::id(),
+            seahash::hash(name.as_bytes())
+        ));
+        fs::create_dir_all(&dir).await.unwrap();
+        Self(dir)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+async fn write_asset(dir: &Path, name: &str, contents: &[u8]) -> AssetFile {
+    let path = dir.join(name);
+    fs::write(&path, contents).await.unwrap();
+    AssetFile::new(dir, PathBuf::from(name)).await.unwrap()
+}
+
+#[tokio::test]
+async fn hits_when_source_and_output_are_unchanged() {
+    let scratch = Scratch::new("hit").await;
+    let out_dir = scratch.path().join("out");
+    fs::create_dir_all(&out_dir).await.unwrap();
+    fs::write(out_dir.join("a.txt"), b"v1").await.unwrap();
+
+    let cache = BuildCache::load(scratch.path()).await.unwrap();
+    let asset = write_asset(scratch.path(), "a.txt", b"v1").await;
+    cache
+        .insert(&asset, "a.txt".to_string(), Vec::new(), "key".to_string())
+        .await
+        .unwrap();
+
+    let hit = cache.lookup(&asset, &out_dir, "key").await.unwrap();
+    assert_eq!(hit.as_deref(), Some("a.txt"));
+}
+
+#[tokio::test]
+async fn misses_when_source_content_changes() {
+    let scratch = Scratch::new("content-change").await;
+    let out_dir = scratch.path().join("out");
+    fs::create_dir_all(&out_dir).await.unwrap();
+    fs::write(out_dir.join("a.txt"), b"v1").await.unwrap();
+
+    let cache = BuildCache::load(scratch.path()).await.unwrap();
+    let asset = write_asset(scratch.path(), "a.txt", b"v1").await;
+    cache
+        .insert(&asset, "a.txt".to_string(), Vec::new(), "key".to_string())
+        .await
+        .unwrap();
+
+    // Overwrite the source after it was cached: neither mtime nor hash still match, so this
+    // must not be served from the stale cache entry.
+    fs::write(&asset.path, b"v2").await.unwrap();
+
+    let hit = cache.lookup(&asset, &out_dir, "key").await.unwrap();
+    assert!(hit.is_none());
+}
+
+#[tokio::test]
+async fn misses_on_cache_key_mismatch() {
+    let scratch = Scratch::new("key-mismatch").await;
+    let out_dir = scratch.path().join("out");
+    fs::create_dir_all(&out_dir).await.unwrap();
+    fs::write(out_dir.join("a.txt"), b"v1").await.unwrap();
+
+    let cache = BuildCache::load(scratch.path()).await.unwrap();
+    let asset = write_asset(scratch.path(), "a.txt", b"v1").await;
+    cache
+        .insert(&asset, "a.txt".to_string(), Vec::new(), "hash=true".to_string())
+        .await
+        .unwrap();
+
+    // Same unchanged source, but looked up under settings (e.g. hashing toggled) that don't
+    // match what this entry was produced under.
+    let hit = cache.lookup(&asset, &out_dir, "hash=false").await.unwrap();
+    assert!(hit.is_none());
+}
+
+#[tokio::test]
+async fn misses_when_output_file_is_missing() {
+    let scratch = Scratch::new("missing-output").await;
+    let out_dir = scratch.path().join("out");
+    fs::create_dir_all(&out_dir).await.unwrap();
+    fs::write(out_dir.join("a.txt"), b"v1").await.unwrap();
+
+    let cache = BuildCache::load(scratch.path()).await.unwrap();
+    let asset = write_asset(scratch.path(), "a.txt", b"v1").await;
+    cache
+        .insert(&asset, "a.txt".to_string(), Vec::new(), "key".to_string())
+        .await
+        .unwrap();
+
+    // The previously-produced output has since been deleted (e.g. a cleaned dist dir); the
+    // cache must not claim a hit for a file that no longer exists.
+    fs::remove_file(out_dir.join("a.txt")).await.unwrap();
+
+    let hit = cache.lookup(&asset, &out_dir, "key").await.unwrap();
+    assert!(hit.is_none());
+}
+
+#[tokio::test]
+async fn misses_when_a_derived_output_file_is_missing() {
+    let scratch = Scratch::new("missing-derived").await;
+    let out_dir = scratch.path().join("out");
+    fs::create_dir_all(&out_dir).await.unwrap();
+    fs::write(out_dir.join("a.txt"), b"v1").await.unwrap();
+    fs::write(out_dir.join("a.txt.map"), b"map").await.unwrap();
+
+    let cache = BuildCache::load(scratch.path()).await.unwrap();
+    let asset = write_asset(scratch.path(), "a.txt", b"v1").await;
+    cache
+        .insert(
+            &asset,
+            "a.txt".to_string(),
+            vec!["a.txt.map".to_string()],
+            "key".to_string(),
+        )
+        .await
+        .unwrap();
+
+    fs::remove_file(out_dir.join("a.txt.map")).await.unwrap();
+
+    let hit = cache.lookup(&asset, &out_dir, "key").await.unwrap();
+    assert!(hit.is_none());
+}
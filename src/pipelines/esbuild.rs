@@ -0,0 +1,107 @@
+//! Bundling and minification of hand-written JS via [esbuild](https://esbuild.github.io).
+//!
+//! This is the backend for a `<script data-trunk data-bundle>` asset: instead of being copied
+//! through to dist as-is, the script is resolved, tree-shaken, and minified into a single hashed
+//! output before the `Js` pipeline's finalizer rewrites the `src`. Trunk manages the `esbuild`
+//! binary the same way it manages `sass`/`tailwindcss`: downloaded and version-pinned into its
+//! tool cache via [`crate::tools`].
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use tokio::process::Command;
+
+use crate::pipelines::Attrs;
+use crate::tools::{self, Application};
+
+const ATTR_BUNDLE: &str = "data-bundle";
+const ATTR_TARGET: &str = "data-target";
+const ATTR_FORMAT: &str = "data-format";
+const ATTR_SOURCEMAP: &str = "data-sourcemap";
+const ATTR_DEFINE: &str = "data-define";
+
+/// Passthrough options for an esbuild invocation, parsed from the attrs on the `<script
+/// data-trunk>` element.
+#[derive(Debug, Default, Clone)]
+pub struct EsbuildOptions {
+    /// `--target`, e.g. `es2020`.
+    pub target: Option<String>,
+    /// `--format`, e.g. `esm`, `iife`.
+    pub format: Option<String>,
+    /// Whether to emit a sourcemap (`--sourcemap`).
+    pub sourcemap: bool,
+    /// Raw `--define:KEY=VALUE` entries.
+    pub define: Vec<String>,
+}
+
+impl EsbuildOptions {
+    /// Whether `attrs` requests bundling at all (`data-bundle` is present and not `"false"`).
+    pub fn is_bundle_requested(attrs: &Attrs) -> bool {
+        attrs.get(ATTR_BUNDLE).map(|val| val != "false").unwrap_or(false)
+    }
+
+    /// Parse the bundling options from the attrs on a `<script data-trunk>` element.
+    pub fn from_attrs(attrs: &Attrs) -> Self {
+        Self {
+            target: attrs.get(ATTR_TARGET).cloned(),
+            format: attrs.get(ATTR_FORMAT).cloned(),
+            sourcemap: attrs
+                .get(ATTR_SOURCEMAP)
+                .map(|val| val != "false")
+                .unwrap_or(false),
+            define: attrs
+                .get(ATTR_DEFINE)
+                .map(|raw| raw.split(',').map(str::trim).map(str::to_string).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Bundle `entry` (and everything it imports) into a single minified file written to `out_path`.
+///
+/// `version` pins the `esbuild` release to download/use, mirroring `Sass`/`TailwindCss`'s tool
+/// management; `offline` disables network access for the tool lookup.
+pub async fn bundle(
+    entry: &Path,
+    out_path: &Path,
+    options: &EsbuildOptions,
+    version: Option<&str>,
+    offline: bool,
+) -> Result<()> {
+    let esbuild = tools::get(Application::Esbuild, version, offline)
+        .await
+        .context("error locating esbuild binary")?;
+
+    let mut cmd = Command::new(&esbuild);
+    cmd.arg(entry)
+        .arg("--bundle")
+        .arg("--minify")
+        .arg(format!("--outfile={}", out_path.display()));
+    if let Some(target) = &options.target {
+        cmd.arg(format!("--target={target}"));
+    }
+    if let Some(format) = &options.format {
+        cmd.arg(format!("--format={format}"));
+    }
+    if options.sourcemap {
+        cmd.arg("--sourcemap");
+    }
+    for define in &options.define {
+        cmd.arg(format!("--define:{define}"));
+    }
+
+    let output = cmd.output().await.context("error spawning esbuild")?;
+    ensure!(
+        output.status.success(),
+        "error bundling {:?} with esbuild: {}",
+        entry,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    Ok(())
+}
+
+/// Compute the hashed output file name for a bundle produced from `file_stem`.
+pub fn bundle_file_name(file_stem: &str, hash: u64) -> PathBuf {
+    PathBuf::from(format!("{file_stem}-{hash:x}.js"))
+}
@@ -0,0 +1,205 @@
+//! The JS asset pipeline, for a hand-written `<script data-trunk src="...">` element.
+//!
+//! By default the script is just copied through to dist as-is. If `data-bundle` is present (see
+//! [`EsbuildOptions::is_bundle_requested`]), it is bundled and minified via
+//! [`crate::pipelines::esbuild`] instead.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use nipper::Document;
+use tokio::fs;
+
+use super::{
+    trunk_script_id_selector, AssetFile, AssetPipeline, Attrs, BuildCache, BuildManifest,
+    IntegrityAlgo, ManifestEntry, PipelineFinalizer, ATTR_CROSSORIGIN, ATTR_INTEGRITY, ATTR_SRC,
+};
+use crate::config::RtcBuild;
+use crate::pipelines::esbuild::{self, EsbuildOptions};
+
+/// A pipeline for a hand-written `<script data-trunk>` asset.
+pub struct Js {
+    id: usize,
+    cfg: Arc<RtcBuild>,
+    asset: AssetFile,
+    /// Bundling options, if `data-bundle` was present on the `<script>` element.
+    bundle: Option<EsbuildOptions>,
+    cache: Arc<BuildCache>,
+    sri: Option<IntegrityAlgo>,
+    manifest: Arc<Mutex<BuildManifest>>,
+}
+
+impl Js {
+    /// Construct a new instance from the attrs found on the `<script data-trunk>` element.
+    pub async fn new(
+        cfg: Arc<RtcBuild>,
+        html_dir: Arc<PathBuf>,
+        attrs: Attrs,
+        id: usize,
+        cache: Arc<BuildCache>,
+        sri: Option<IntegrityAlgo>,
+        manifest: Arc<Mutex<BuildManifest>>,
+    ) -> Result<Self> {
+        let src = attrs
+            .get(ATTR_SRC)
+            .context("all <script data-trunk .../> elements must have a `src` attribute")?;
+        let asset = AssetFile::new(&html_dir, PathBuf::from(src)).await?;
+        let bundle =
+            EsbuildOptions::is_bundle_requested(&attrs).then(|| EsbuildOptions::from_attrs(&attrs));
+        Ok(Self {
+            id,
+            cfg,
+            asset,
+            bundle,
+            cache,
+            sri,
+            manifest,
+        })
+    }
+
+    /// Bundle this script via esbuild and record the result in the build cache, so an unchanged
+    /// source under the same `options` and esbuild version can skip re-invoking esbuild on the
+    /// next build.
+    ///
+    /// The cache-busting hash is taken from the *bundled* output, not the pre-bundle source: two
+    /// `<script data-trunk data-bundle>` tags pointing at the same source file with different
+    /// `options` (or an esbuild version bump that changes its output for unchanged input) must
+    /// not collide on the same output file name.
+    async fn bundle_via_esbuild(&self, options: &EsbuildOptions, cache_key: &str) -> Result<String> {
+        let provisional_path = self.cfg.final_dist.join(format!(
+            "{}.bundle.tmp",
+            self.asset.file_stem.to_string_lossy()
+        ));
+        esbuild::bundle(
+            &self.asset.path,
+            &provisional_path,
+            options,
+            self.cfg.tools.esbuild.as_deref(),
+            self.cfg.offline,
+        )
+        .await?;
+
+        let bytes = fs::read(&provisional_path)
+            .await
+            .with_context(|| format!("error reading bundled output {provisional_path:?}"))?;
+        let hash = seahash::hash(&bytes);
+        let out_name = esbuild::bundle_file_name(&self.asset.file_stem.to_string_lossy(), hash);
+        let out_path = self.cfg.final_dist.join(&out_name);
+        fs::rename(&provisional_path, &out_path)
+            .await
+            .with_context(|| format!("error renaming bundled output to {out_path:?}"))?;
+
+        let mut derived = Vec::new();
+        if options.sourcemap {
+            let provisional_map = append_extension(&provisional_path, "map");
+            if fs::metadata(&provisional_map).await.is_ok() {
+                let out_map = append_extension(&out_path, "map");
+                fs::rename(&provisional_map, &out_map)
+                    .await
+                    .with_context(|| format!("error renaming bundled sourcemap to {out_map:?}"))?;
+                derived.push(
+                    out_map
+                        .file_name()
+                        .context("bundled sourcemap has no file name")?
+                        .to_string_lossy()
+                        .into_owned(),
+                );
+            }
+        }
+
+        let out_name = out_name.to_string_lossy().into_owned();
+        self.cache
+            .insert(&self.asset, out_name.clone(), derived, cache_key.to_string())
+            .await?;
+        Ok(out_name)
+    }
+}
+
+/// Append `extra_ext` to `path`'s existing extension, e.g. `out.js` -> `out.js.map`.
+fn append_extension(path: &std::path::Path, extra_ext: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extra_ext);
+    PathBuf::from(name)
+}
+
+#[async_trait]
+impl AssetPipeline for Js {
+    async fn run(self: Box<Self>) -> Result<Box<dyn PipelineFinalizer>> {
+        let (file_name, integrity) = match &self.bundle {
+            Some(options) => {
+                // Distinct `data-bundle` option sets (or esbuild versions) on the same source
+                // file are distinct outputs, so both are part of the cache key: an entry
+                // produced under other options or another pinned version is a miss, never a hit.
+                // An unpinned esbuild is keyed as "default".
+                let cache_key = format!(
+                    "bundle={options:?};esbuild={}",
+                    self.cfg.tools.esbuild.as_deref().unwrap_or("default")
+                );
+                let cached = self
+                    .cache
+                    .lookup(&self.asset, &self.cfg.final_dist, &cache_key)
+                    .await?;
+                let out_name = match cached {
+                    Some(out_name) => out_name,
+                    None => {
+                        self.bundle_via_esbuild(options, &cache_key)
+                            .await
+                            .context("error bundling script with esbuild")?
+                    }
+                };
+                let out_path = self.cfg.final_dist.join(&out_name);
+                let integrity = match self.sri {
+                    Some(algo) => {
+                        let bytes = fs::read(&out_path).await.with_context(|| {
+                            format!("error reading bundled script for integrity hash {out_path:?}")
+                        })?;
+                        Some(algo.integrity_value(&bytes))
+                    }
+                    None => None,
+                };
+                (out_name, integrity)
+            }
+            None => {
+                self.asset
+                    .copy_with_integrity(&self.cfg.final_dist, true, Some(&self.cache), self.sri)
+                    .await?
+            }
+        };
+
+        self.manifest.lock().unwrap().insert(ManifestEntry {
+            name: self.asset.file_name.to_string_lossy().into_owned(),
+            hashed_name: file_name.clone(),
+            integrity: integrity.clone(),
+        });
+
+        Ok(Box::new(JsOutput {
+            id: self.id,
+            file_name,
+            integrity,
+        }))
+    }
+}
+
+/// The finalized output of a [`Js`] build, ready to be patched into the DOM.
+pub struct JsOutput {
+    pub id: usize,
+    pub file_name: String,
+    /// The `integrity="sha384-…"` value to set on the `<script>` tag, if SRI was enabled.
+    pub integrity: Option<String>,
+}
+
+#[async_trait]
+impl PipelineFinalizer for JsOutput {
+    async fn finalize(self: Box<Self>, dom: &mut Document) -> Result<()> {
+        let sel = dom.select(&trunk_script_id_selector(self.id));
+        sel.attr(ATTR_SRC, &self.file_name);
+        if let Some(integrity) = &self.integrity {
+            sel.attr(ATTR_INTEGRITY, integrity);
+            sel.attr(ATTR_CROSSORIGIN, "anonymous");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+//! Subresource Integrity digests and the `manifest.json` build manifest.
+//!
+//! Mirrors how rustdoc separates content-hashed static files (safe for
+//! `Cache-Control: immutable`) from predictably-named files: hashed assets get an `integrity`
+//! attribute and an entry in `manifest.json`, so a downstream server/CDN can tell them apart from
+//! `index.html`, which must never be cached immutably.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384};
+use tokio::fs;
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// The hash algorithm used to compute a Subresource Integrity digest.
+///
+/// The cache-busting hash already applied to file names (`seahash`) is fine for that purpose, but
+/// is not a cryptographic digest and must not be used for the `integrity` attribute.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IntegrityAlgo {
+    Sha256,
+    Sha384,
+}
+
+impl IntegrityAlgo {
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Sha384 => "sha384",
+        }
+    }
+
+    /// Compute the `integrity="<alg>-<digest>"` attribute value for `bytes`.
+    pub fn integrity_value(self, bytes: &[u8]) -> String {
+        let digest: Vec<u8> = match self {
+            Self::Sha256 => Sha256::digest(bytes).to_vec(),
+            Self::Sha384 => Sha384::digest(bytes).to_vec(),
+        };
+        format!("{}-{}", self.prefix(), BASE64.encode(digest))
+    }
+}
+
+/// A single asset emitted to the dist dir, recorded so downstream servers/CDNs can key cache
+/// headers off of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The asset's logical name prior to content-hashing, e.g. `app.css`.
+    pub name: String,
+    /// The name the asset was actually emitted under in the dist dir, e.g. `app-abcd1234.css`.
+    pub hashed_name: String,
+    /// The SRI `integrity` value for this asset, if integrity hashing was enabled.
+    pub integrity: Option<String>,
+}
+
+/// The `manifest.json` written to the dist root, listing every emitted file, its logical name,
+/// its hashed name, and (if enabled) its integrity digest.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BuildManifest {
+    assets: BTreeMap<String, ManifestEntry>,
+}
+
+impl BuildManifest {
+    /// Record an emitted asset, keyed by its hashed (actually emitted) name.
+    ///
+    /// Keying by `name` instead would let two unrelated assets that merely share a basename
+    /// (e.g. `icon.png` under two different source dirs) silently clobber each other's entry;
+    /// `hashed_name` is guaranteed unique because it is the literal file Trunk wrote into the
+    /// (flat) dist dir.
+    pub fn insert(&mut self, entry: ManifestEntry) {
+        self.assets.insert(entry.hashed_name.clone(), entry);
+    }
+
+    /// Write this manifest to `manifest.json` in `dist_dir`.
+    ///
+    /// The caller owns calling this exactly once, after every asset's finalizer has run --
+    /// nothing in `crate::pipelines` calls it itself. That caller is the top-level build driver
+    /// (along with whatever `Trunk.toml`/CLI flag gates SRI/manifest generation in the first
+    /// place), and neither exists in this tree, so this method currently has no call site.
+    pub async fn write(&self, dist_dir: &Path) -> Result<()> {
+        let raw =
+            serde_json::to_string_pretty(self).context("error serializing build manifest")?;
+        let path = dist_dir.join(MANIFEST_FILE_NAME);
+        fs::write(&path, raw)
+            .await
+            .with_context(|| format!("error writing build manifest {:?}", &path))
+    }
+}
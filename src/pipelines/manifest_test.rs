@@ -0,0 +1,35 @@
+//! Tests for [`super::IntegrityAlgo`]'s digest computation.
+
+use super::IntegrityAlgo;
+
+#[test]
+fn sha256_matches_known_vector() {
+    assert_eq!(
+        IntegrityAlgo::Sha256.integrity_value(b"hello world"),
+        "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek="
+    );
+}
+
+#[test]
+fn sha384_matches_known_vector() {
+    assert_eq!(
+        IntegrityAlgo::Sha384.integrity_value(b"hello world"),
+        "sha384-/b2OdaZ/KfcBpOBAOF4uI5hjA+oQI5IRr5B/y7g1eLPkF8txzmRu/QgZ3YwIjeG9"
+    );
+}
+
+#[test]
+fn different_content_yields_different_digest() {
+    let a = IntegrityAlgo::Sha256.integrity_value(b"trunk");
+    let b = IntegrityAlgo::Sha256.integrity_value(b"trunk!");
+    assert_ne!(a, b);
+}
+
+#[test]
+fn different_algo_yields_different_prefix_and_digest() {
+    let sha256 = IntegrityAlgo::Sha256.integrity_value(b"trunk");
+    let sha384 = IntegrityAlgo::Sha384.integrity_value(b"trunk");
+    assert!(sha256.starts_with("sha256-"));
+    assert!(sha384.starts_with("sha384-"));
+    assert_ne!(sha256, sha384);
+}
@@ -1,12 +1,21 @@
+mod cache;
+#[cfg(test)]
+mod cache_test;
 mod copy_dir;
 mod copy_file;
 #[cfg(test)]
 mod copy_file_test;
 mod css;
+mod esbuild;
 mod html;
 mod icon;
 mod inline;
 mod js;
+mod manifest;
+#[cfg(test)]
+mod manifest_test;
+mod pagefind;
+mod registry;
 mod rust;
 mod sass;
 mod tailwind_css;
@@ -14,9 +23,10 @@ mod tailwind_css;
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use anyhow::{bail, ensure, Context, Result};
+use async_trait::async_trait;
 pub use html::HtmlPipeline;
 use nipper::Document;
 use serde::Deserialize;
@@ -26,12 +36,16 @@ use tokio::task::JoinHandle;
 
 use crate::common::path_exists;
 use crate::config::RtcBuild;
+pub use crate::pipelines::cache::BuildCache;
 use crate::pipelines::copy_dir::{CopyDir, CopyDirOutput};
 use crate::pipelines::copy_file::{CopyFile, CopyFileOutput};
 use crate::pipelines::css::{Css, CssOutput};
 use crate::pipelines::icon::{Icon, IconOutput};
 use crate::pipelines::inline::{Inline, InlineOutput};
-use crate::pipelines::js::{Js, JsOutput};
+use crate::pipelines::js::Js;
+pub use crate::pipelines::manifest::{BuildManifest, IntegrityAlgo, ManifestEntry};
+use crate::pipelines::pagefind::Pagefind;
+pub use crate::pipelines::registry::{lookup_pipeline, register_pipeline, PipelineCtorArgs};
 use crate::pipelines::rust::{RustApp, RustAppOutput};
 use crate::pipelines::sass::{Sass, SassOutput};
 use crate::pipelines::tailwind_css::{TailwindCss, TailwindCssOutput};
@@ -41,12 +55,37 @@ const ATTR_HREF: &str = "href";
 const ATTR_SRC: &str = "src";
 const ATTR_TYPE: &str = "type";
 const ATTR_REL: &str = "rel";
+const ATTR_INTEGRITY: &str = "integrity";
+const ATTR_CROSSORIGIN: &str = "crossorigin";
 const SNIPPETS_DIR: &str = "snippets";
 const TRUNK_ID: &str = "data-trunk-id";
 
 /// A mapping of all attrs associated with a specific `<link data-trunk .../>` element.
 pub type Attrs = HashMap<String, String>;
 
+/// A build pipeline for an asset type, built-in or third-party.
+///
+/// Every [`PipelineStage::Build`] [`TrunkAsset`] variant (all but `Pagefind`) is boxed as an
+/// `AssetPipeline` and driven through [`Self::run`] by
+/// [`TrunkAsset::spawn`], so the enum is a thin dispatcher over one uniform execution path rather
+/// than two parallel ones. Third-party pipelines plug into the exact same path: a user registers a
+/// factory for some `rel`/`type` value via [`register_pipeline`], which is constructed from the
+/// element's [`Attrs`] via a [`PipelineCtorArgs`], without editing the built-in [`TrunkAsset`]
+/// enum at all. A pipeline is run to completion and must produce a [`PipelineFinalizer`] that will
+/// patch the DOM once all assets are built.
+#[async_trait]
+pub trait AssetPipeline: Send {
+    /// Run this pipeline to completion, returning a finalizer for the generated output.
+    async fn run(self: Box<Self>) -> Result<Box<dyn PipelineFinalizer>>;
+}
+
+/// The output half of an [`AssetPipeline`], responsible for rewriting the finalized HTML.
+#[async_trait]
+pub trait PipelineFinalizer: Send {
+    /// Patch `dom` with whatever `<link>`/`<script>` changes this pipeline's output requires.
+    async fn finalize(self: Box<Self>, dom: &mut Document) -> Result<()>;
+}
+
 /// A reference to a trunk asset.
 pub enum TrunkAssetReference {
     Link(Attrs),
@@ -70,16 +109,36 @@ pub enum TrunkAsset {
     CopyFile(CopyFile),
     CopyDir(CopyDir),
     RustApp(RustApp),
+    Pagefind(Pagefind),
+    /// A third-party asset pipeline registered via [`register_pipeline`].
+    External(Box<dyn AssetPipeline>),
 }
 
 impl TrunkAsset {
     /// Construct a new instance.
+    ///
+    /// `cache` is the single [`BuildCache`] for the whole build -- the caller loads it once (e.g.
+    /// via [`BuildCache::load`] alongside reading `cfg`) and passes the same `Arc` into every
+    /// `from_html` call, so pipelines that shell out to an external tool (`Sass`, `TailwindCss`,
+    /// `RustApp`) can look up a prior run's output and skip re-invoking that tool entirely.
+    ///
+    /// `sri` is the SRI digest algorithm to compute for each asset emitted by the `Css`, `Sass`,
+    /// `TailwindCss`, `Icon`, `CopyFile` and `Js` pipelines, or `None` if Subresource Integrity
+    /// was not opted into for this build. `manifest` is the single [`BuildManifest`] for the
+    /// whole build; those same pipelines record a [`ManifestEntry`] into it. `RustApp`,
+    /// `CopyDir`, `Inline`, `Pagefind` and third-party pipelines receive neither, so their
+    /// outputs get no digest and no manifest entry. The caller writes the manifest out via
+    /// [`BuildManifest::write`] once all assets have finished, alongside the `final_dist` it just
+    /// finished populating.
     pub async fn from_html(
         cfg: Arc<RtcBuild>,
         html_dir: Arc<PathBuf>,
         ignore_chan: Option<mpsc::Sender<PathBuf>>,
         reference: TrunkAssetReference,
         id: usize,
+        cache: Arc<BuildCache>,
+        sri: Option<IntegrityAlgo>,
+        manifest: Arc<Mutex<BuildManifest>>,
     ) -> Result<Self> {
         match reference {
             TrunkAssetReference::Link(attrs) => {
@@ -89,57 +148,175 @@ impl TrunkAsset {
                 )?;
                 Ok(match rel.as_str() {
                     Sass::TYPE_SASS | Sass::TYPE_SCSS => {
-                        Self::Sass(Sass::new(cfg, html_dir, attrs, id).await?)
+                        Self::Sass(
+                            Sass::new(cfg, html_dir, attrs, id, cache, sri, manifest).await?,
+                        )
+                    }
+                    Icon::TYPE_ICON => {
+                        Self::Icon(Icon::new(cfg, html_dir, attrs, id, sri, manifest).await?)
                     }
-                    Icon::TYPE_ICON => Self::Icon(Icon::new(cfg, html_dir, attrs, id).await?),
                     Inline::TYPE_INLINE => Self::Inline(Inline::new(html_dir, attrs, id).await?),
-                    Css::TYPE_CSS => Self::Css(Css::new(cfg, html_dir, attrs, id).await?),
-                    CopyFile::TYPE_COPY_FILE => {
-                        Self::CopyFile(CopyFile::new(cfg, html_dir, attrs, id).await?)
+                    Css::TYPE_CSS => {
+                        Self::Css(Css::new(cfg, html_dir, attrs, id, sri, manifest).await?)
                     }
+                    CopyFile::TYPE_COPY_FILE => Self::CopyFile(
+                        CopyFile::new(cfg, html_dir, attrs, id, sri, manifest).await?,
+                    ),
                     CopyDir::TYPE_COPY_DIR => {
                         Self::CopyDir(CopyDir::new(cfg, html_dir, attrs, id).await?)
                     }
                     RustApp::TYPE_RUST_APP => {
-                        Self::RustApp(RustApp::new(cfg, html_dir, ignore_chan, attrs, id).await?)
+                        Self::RustApp(
+                            RustApp::new(cfg, html_dir, ignore_chan, attrs, id, cache).await?,
+                        )
                     }
                     TailwindCss::TYPE_TAILWIND_CSS => {
-                        Self::TailwindCss(TailwindCss::new(cfg, html_dir, attrs, id).await?)
+                        Self::TailwindCss(
+                            TailwindCss::new(cfg, html_dir, attrs, id, cache, sri, manifest)
+                                .await?,
+                        )
                     }
-                    _ => bail!(
-                        r#"unknown <link data-trunk .../> attr value `rel="{}"`; please ensure the value is lowercase and is a supported asset type"#,
-                        rel
-                    ),
+                    Pagefind::TYPE_PAGEFIND => {
+                        Self::Pagefind(Pagefind::new(cfg, attrs, id).await?)
+                    }
+                    rel => match lookup_pipeline(rel) {
+                        Some(factory) => Self::External(
+                            factory(PipelineCtorArgs {
+                                cfg,
+                                html_dir,
+                                attrs,
+                                id,
+                            })
+                            .await?,
+                        ),
+                        None => bail!(
+                            r#"unknown <link data-trunk .../> attr value `rel="{}"`; please ensure the value is lowercase and is a supported asset type"#,
+                            rel
+                        ),
+                    },
                 })
             }
             TrunkAssetReference::Script(attrs) => {
-                Ok(Self::Js(Js::new(cfg, html_dir, attrs, id).await?))
+                if let Some(factory) = attrs.get(ATTR_TYPE).and_then(|ty| lookup_pipeline(ty)) {
+                    return Ok(Self::External(
+                        factory(PipelineCtorArgs {
+                            cfg,
+                            html_dir,
+                            attrs,
+                            id,
+                        })
+                        .await?,
+                    ));
+                }
+                Ok(Self::Js(
+                    Js::new(cfg, html_dir, attrs, id, cache, sri, manifest).await?,
+                ))
             }
         }
     }
 
-    /// Spawn the build pipeline for this asset.
-    pub fn spawn(self) -> JoinHandle<Result<TrunkAssetPipelineOutput>> {
+    /// The [`PipelineStage`] this asset's pipeline must run in.
+    ///
+    /// Everything except [`Self::Pagefind`] runs in [`PipelineStage::Build`], concurrently with
+    /// every other asset. [`Pagefind`] is a [`PipelineStage::PostBuild`] pipeline and must never
+    /// be passed to [`Self::spawn`] -- extract it first via [`Self::into_post_build`].
+    pub fn stage(&self) -> PipelineStage {
+        match self {
+            Self::Pagefind(_) => PipelineStage::PostBuild,
+            _ => PipelineStage::Build,
+        }
+    }
+
+    /// If this is a [`PipelineStage::PostBuild`] asset, pull it out as a [`Pagefind`] so the
+    /// caller can run it separately (via [`Pagefind::run_post_build`]) once every
+    /// [`PipelineStage::Build`] asset's finalizer has written its output to `final_dist`.
+    /// Otherwise, hands `self` back unchanged so it can be passed to [`Self::spawn`].
+    pub fn into_post_build(self) -> Result<Pagefind, Self> {
         match self {
-            Self::Css(inner) => inner.spawn(),
-            Self::Sass(inner) => inner.spawn(),
-            Self::TailwindCss(inner) => inner.spawn(),
-            Self::Js(inner) => inner.spawn(),
-            Self::Icon(inner) => inner.spawn(),
-            Self::Inline(inner) => inner.spawn(),
-            Self::CopyFile(inner) => inner.spawn(),
-            Self::CopyDir(inner) => inner.spawn(),
-            Self::RustApp(inner) => inner.spawn(),
+            Self::Pagefind(inner) => Ok(inner),
+            other => Err(other),
         }
     }
+
+    /// Spawn the build pipeline for this asset.
+    ///
+    /// Every [`PipelineStage::Build`] variant -- built-in or third-party -- is boxed as a uniform
+    /// [`AssetPipeline`] and driven through [`AssetPipeline::run`], so this is a thin dispatcher
+    /// rather than a second, parallel execution mechanism living alongside the registry-based one
+    /// used for external pipelines.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called on a [`PipelineStage::PostBuild`] asset; callers must filter those out via
+    /// [`Self::into_post_build`] first.
+    pub fn spawn(self) -> JoinHandle<Result<Box<dyn PipelineFinalizer>>> {
+        let pipeline: Box<dyn AssetPipeline> = match self {
+            Self::Css(inner) => Box::new(inner),
+            Self::Sass(inner) => Box::new(inner),
+            Self::TailwindCss(inner) => Box::new(inner),
+            Self::Js(inner) => Box::new(inner),
+            Self::Icon(inner) => Box::new(inner),
+            Self::Inline(inner) => Box::new(inner),
+            Self::CopyFile(inner) => Box::new(inner),
+            Self::CopyDir(inner) => Box::new(inner),
+            Self::RustApp(inner) => Box::new(inner),
+            Self::Pagefind(_) => {
+                unreachable!("PostBuild assets must be extracted via `into_post_build` first")
+            }
+            Self::External(inner) => inner,
+        };
+        tokio::spawn(async move { pipeline.run().await })
+    }
+}
+
+/// Bridges a built-in pipeline's existing `spawn`/`finalize` pair onto the uniform
+/// [`AssetPipeline`]/[`PipelineFinalizer`] traits, so built-ins need not be rewritten from
+/// scratch to join the same dispatch path as third-party pipelines registered via
+/// [`register_pipeline`].
+macro_rules! impl_builtin_pipeline {
+    ($ty:ty, $out_ty:ty, $variant:ident) => {
+        #[async_trait]
+        impl AssetPipeline for $ty {
+            async fn run(self: Box<Self>) -> Result<Box<dyn PipelineFinalizer>> {
+                match (*self)
+                    .spawn()
+                    .await
+                    .context("pipeline task panicked")??
+                {
+                    TrunkAssetPipelineOutput::$variant(out) => Ok(Box::new(out)),
+                    _ => unreachable!(
+                        "{} only ever produces its own output variant",
+                        stringify!($ty)
+                    ),
+                }
+            }
+        }
+
+        #[async_trait]
+        impl PipelineFinalizer for $out_ty {
+            async fn finalize(self: Box<Self>, dom: &mut Document) -> Result<()> {
+                (*self).finalize(dom).await
+            }
+        }
+    };
 }
 
-/// The output of a `<trunk-link/>` asset pipeline.
+impl_builtin_pipeline!(Css, CssOutput, Css);
+impl_builtin_pipeline!(Sass, SassOutput, Sass);
+impl_builtin_pipeline!(TailwindCss, TailwindCssOutput, TailwindCss);
+impl_builtin_pipeline!(Icon, IconOutput, Icon);
+impl_builtin_pipeline!(Inline, InlineOutput, Inline);
+impl_builtin_pipeline!(CopyFile, CopyFileOutput, CopyFile);
+impl_builtin_pipeline!(CopyDir, CopyDirOutput, CopyDir);
+impl_builtin_pipeline!(RustApp, RustAppOutput, RustApp);
+
+/// The output of a built-in pipeline, as produced by its own (pre-[`AssetPipeline`]) `spawn`
+/// method. [`impl_builtin_pipeline`] unwraps this internally to bridge onto [`PipelineFinalizer`];
+/// nothing outside this module should need to match on it directly.
 pub enum TrunkAssetPipelineOutput {
     Css(CssOutput),
     Sass(SassOutput),
     TailwindCss(TailwindCssOutput),
-    Js(JsOutput),
     Icon(IconOutput),
     Inline(InlineOutput),
     CopyFile(CopyFileOutput),
@@ -153,7 +330,6 @@ impl TrunkAssetPipelineOutput {
             TrunkAssetPipelineOutput::Css(out) => out.finalize(dom).await,
             TrunkAssetPipelineOutput::Sass(out) => out.finalize(dom).await,
             TrunkAssetPipelineOutput::TailwindCss(out) => out.finalize(dom).await,
-            TrunkAssetPipelineOutput::Js(out) => out.finalize(dom).await,
             TrunkAssetPipelineOutput::Icon(out) => out.finalize(dom).await,
             TrunkAssetPipelineOutput::Inline(out) => out.finalize(dom).await,
             TrunkAssetPipelineOutput::CopyFile(out) => out.finalize(dom).await,
@@ -226,6 +402,25 @@ impl AssetFile {
     /// The base file name (stripped path, without any parent folders) is returned if the operation
     /// was successful.
     pub async fn copy(&self, to_dir: &Path, with_hash: bool) -> Result<String> {
+        self.copy_with_cache(to_dir, with_hash, None).await
+    }
+
+    /// Like [`Self::copy`], but consults `cache` first and skips the read/write entirely if an
+    /// up-to-date output already exists in `to_dir` from a previous build.
+    pub async fn copy_with_cache(
+        &self,
+        to_dir: &Path,
+        with_hash: bool,
+        cache: Option<&BuildCache>,
+    ) -> Result<String> {
+        let cache_key = format!("hash={with_hash}");
+
+        if let Some(cache) = cache {
+            if let Some(file_name) = cache.lookup(self, to_dir, &cache_key).await? {
+                return Ok(file_name);
+            }
+        }
+
         let bytes = fs::read(&self.path)
             .await
             .with_context(|| format!("error reading file for copying {:?}", &self.path))?;
@@ -247,9 +442,39 @@ impl AssetFile {
             .await
             .with_context(|| format!("error copying file {:?} to {:?}", &self.path, &file_path))?;
 
+        if let Some(cache) = cache {
+            cache
+                .insert(self, file_name.clone(), Vec::new(), cache_key)
+                .await?;
+        }
+
         Ok(file_name)
     }
 
+    /// Like [`Self::copy_with_cache`], but also computes a Subresource Integrity digest over the
+    /// emitted file when `integrity` is `Some`. Returns the emitted file name along with the
+    /// `integrity="<alg>-<digest>"` attribute value, if requested.
+    pub async fn copy_with_integrity(
+        &self,
+        to_dir: &Path,
+        with_hash: bool,
+        cache: Option<&BuildCache>,
+        integrity: Option<IntegrityAlgo>,
+    ) -> Result<(String, Option<String>)> {
+        let file_name = self.copy_with_cache(to_dir, with_hash, cache).await?;
+        let integrity = match integrity {
+            Some(algo) => {
+                let file_path = to_dir.join(&file_name);
+                let bytes = fs::read(&file_path)
+                    .await
+                    .with_context(|| format!("error reading file for integrity hash {:?}", &file_path))?;
+                Some(algo.integrity_value(&bytes))
+            }
+            None => None,
+        };
+        Ok((file_name, integrity))
+    }
+
     /// Read the content of this asset to a String.
     pub async fn read_to_string(&self) -> Result<String> {
         fs::read_to_string(&self.path)
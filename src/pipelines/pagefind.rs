@@ -0,0 +1,116 @@
+//! The Pagefind asset pipeline.
+//!
+//! Registered under `<link data-trunk rel="pagefind">`, this is a [`PipelineStage::PostBuild`]
+//! pipeline: unlike [`super::TrunkAsset`]'s other variants, it is never handed to
+//! [`super::TrunkAsset::spawn`]'s concurrent build pool. [`super::TrunkAsset::into_post_build`]
+//! pulls it out of that pool instead, so the caller can run [`Pagefind::run_post_build`] only
+//! after every other asset's finalizer has patched the DOM and its output has actually been
+//! written to `final_dist` -- running `pagefind --site <dist>` concurrently with the rest of the
+//! build would index a partially-written (or stale) dist dir nondeterministically.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{ensure, Context, Result};
+use nipper::Document;
+use tokio::process::Command;
+
+use super::{trunk_id_selector, Attrs, ATTR_HREF, TRUNK_ID};
+use crate::config::RtcBuild;
+use crate::tools::{self, Application};
+
+const ATTR_PAGEFIND_ARGS: &str = "data-pagefind-args";
+const ATTR_PAGEFIND_UI: &str = "data-pagefind-ui";
+const PAGEFIND_BUNDLE_DIR: &str = "pagefind";
+
+/// A pipeline that runs [Pagefind](https://pagefind.app) over the dist directory once all other
+/// assets have been written, giving Trunk-built SPAs/MPAs zero-config client-side full-text
+/// search.
+pub struct Pagefind {
+    id: usize,
+    cfg: Arc<RtcBuild>,
+    /// Extra flags to pass through to the `pagefind` CLI, taken from `data-pagefind-args`.
+    extra_args: Vec<String>,
+    /// Whether to inject the Pagefind UI bundle's `<link>`/`<script>` tags, taken from
+    /// `data-pagefind-ui` (defaults to `true`).
+    inject_ui: bool,
+}
+
+impl Pagefind {
+    pub const TYPE_PAGEFIND: &'static str = "pagefind";
+
+    /// Construct a new instance from the attrs found on the `<link data-trunk rel="pagefind">`
+    /// element.
+    pub async fn new(cfg: Arc<RtcBuild>, attrs: Attrs, id: usize) -> Result<Self> {
+        let extra_args = attrs
+            .get(ATTR_PAGEFIND_ARGS)
+            .map(|args| args.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default();
+        let inject_ui = attrs
+            .get(ATTR_PAGEFIND_UI)
+            .map(|val| val != "false")
+            .unwrap_or(true);
+        Ok(Self {
+            id,
+            cfg,
+            extra_args,
+            inject_ui,
+        })
+    }
+
+    /// Run the pagefind index build. Must only be called once every other asset's finalizer has
+    /// already patched the DOM and written its output to `final_dist` -- see the module docs.
+    pub async fn run_post_build(self) -> Result<PagefindOutput> {
+        let version = self.cfg.tools.pagefind.as_deref();
+        let pagefind = tools::get(Application::Pagefind, version, self.cfg.offline)
+            .await
+            .context("error locating pagefind binary")?;
+
+        let dist = self.cfg.final_dist.as_path();
+        let output = Command::new(&pagefind)
+            .arg("--site")
+            .arg(dist)
+            .args(&self.extra_args)
+            .output()
+            .await
+            .context("error spawning pagefind")?;
+        ensure!(
+            output.status.success(),
+            "error building pagefind search index: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        Ok(PagefindOutput {
+            id: self.id,
+            inject_ui: self.inject_ui,
+        })
+    }
+}
+
+/// The finalized output of a [`Pagefind`] build, ready to be patched into the DOM.
+pub struct PagefindOutput {
+    pub id: usize,
+    pub inject_ui: bool,
+}
+
+impl PagefindOutput {
+    pub async fn finalize(self, dom: &mut Document) -> Result<()> {
+        // Remove the placeholder `<link data-trunk rel="pagefind">` element.
+        dom.select(&trunk_id_selector(self.id)).remove();
+
+        if self.inject_ui {
+            let ui_css = format!("{PAGEFIND_BUNDLE_DIR}/pagefind-ui.css");
+            let ui_js = format!("{PAGEFIND_BUNDLE_DIR}/pagefind-ui.js");
+            dom.select("head").append_html(format!(
+                r#"<link rel="stylesheet" {ATTR_HREF}="{ui_css}">"#
+            ));
+            dom.select("body").append_html(format!(
+                r#"<script {TRUNK_ID}="{}" src="{ui_js}"></script>
+<script>window.addEventListener("DOMContentLoaded",()=>{{new PagefindUI({{element:"#search"}});}});</script>"#,
+                self.id
+            ));
+        }
+
+        Ok(())
+    }
+}
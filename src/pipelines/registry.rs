@@ -0,0 +1,50 @@
+//! A registry allowing third parties to plug custom asset pipelines into Trunk's `rel`/`type`
+//! dispatch without editing the built-in [`super::TrunkAsset`] enum.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use anyhow::Result;
+
+use crate::config::RtcBuild;
+use crate::pipelines::{Attrs, AssetPipeline};
+
+/// The arguments passed to a [`PipelineFactory`] when constructing a third-party pipeline.
+pub struct PipelineCtorArgs {
+    /// The runtime config for the current build.
+    pub cfg: Arc<RtcBuild>,
+    /// The directory containing the source HTML document.
+    pub html_dir: Arc<PathBuf>,
+    /// All attrs found on the `<link data-trunk .../>` or `<script data-trunk .../>` element.
+    pub attrs: Attrs,
+    /// The unique ID assigned to this asset reference.
+    pub id: usize,
+}
+
+type PipelineFuture = Pin<Box<dyn Future<Output = Result<Box<dyn AssetPipeline>>> + Send>>;
+
+/// A factory function constructing a boxed [`AssetPipeline`] for some registered `rel`/`type`
+/// value.
+pub type PipelineFactory = fn(PipelineCtorArgs) -> PipelineFuture;
+
+fn registry() -> &'static Mutex<HashMap<&'static str, PipelineFactory>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<&'static str, PipelineFactory>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Register a third-party asset pipeline under the given `rel`/`type` value.
+///
+/// Once registered, `<link data-trunk rel="...">` elements using this value are dispatched to
+/// `factory` instead of failing with an "unknown asset type" error. Registering the same `rel`
+/// twice overwrites the previous factory.
+pub fn register_pipeline(rel: &'static str, factory: PipelineFactory) {
+    registry().lock().unwrap().insert(rel, factory);
+}
+
+/// Look up the factory registered for `rel`, if any.
+pub fn lookup_pipeline(rel: &str) -> Option<PipelineFactory> {
+    registry().lock().unwrap().get(rel).copied()
+}
@@ -0,0 +1,242 @@
+//! Locating and downloading the external CLI tools Trunk's built-in pipelines shell out to.
+//!
+//! Each [`Application`] is version-pinned per build (via the `[tools]` section of `Trunk.toml` or
+//! the matching CLI flag) and cached on disk under Trunk's tool cache so repeat builds -- and
+//! fresh checkouts in CI -- don't need the tool pre-installed.
+
+use std::env;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
+use tar::Archive;
+use tokio::fs;
+
+/// A third-party CLI application a built-in pipeline can download and invoke, pinned to a
+/// specific version per build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Application {
+    Sass,
+    TailwindCss,
+    /// The [Pagefind](https://pagefind.app) search-index builder, used by [`crate::pipelines::pagefind`].
+    Pagefind,
+    /// The [esbuild](https://esbuild.github.io) bundler, used by [`crate::pipelines::esbuild`].
+    Esbuild,
+}
+
+/// Where, within its release archive, an application's binary lives; [`Layout::RawBinary`] means
+/// the artifact at the download URL *is* the binary, with nothing to unpack.
+enum Layout {
+    RawBinary,
+    TarGz { entry_name: &'static str },
+}
+
+impl Application {
+    /// The binary name to look for/invoke once cached.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Sass => "sass",
+            Self::TailwindCss => "tailwindcss",
+            Self::Pagefind => "pagefind",
+            Self::Esbuild => "esbuild",
+        }
+    }
+
+    /// The version downloaded when the user has not pinned one explicitly via `Trunk.toml`/CLI
+    /// flag.
+    fn default_version(self) -> &'static str {
+        match self {
+            Self::Sass => "1.77.6",
+            Self::TailwindCss => "3.4.4",
+            Self::Pagefind => "1.1.0",
+            Self::Esbuild => "0.21.5",
+        }
+    }
+
+    /// The download URL for `version` on the host's OS/arch, and how the binary is laid out once
+    /// downloaded.
+    fn download(self, version: &str) -> Result<(String, Layout)> {
+        let (os, arch) = (env::consts::OS, env::consts::ARCH);
+        match self {
+            Self::Sass => {
+                let platform = dart_sass_platform(os, arch)?;
+                Ok((
+                    format!(
+                        "https://github.com/sass/dart-sass/releases/download/{version}/dart-sass-{version}-{platform}.tar.gz"
+                    ),
+                    Layout::TarGz {
+                        entry_name: "dart-sass/sass",
+                    },
+                ))
+            }
+            Self::TailwindCss => {
+                let platform = tailwindcss_platform(os, arch)?;
+                Ok((
+                    format!(
+                        "https://github.com/tailwindlabs/tailwindcss/releases/download/v{version}/tailwindcss-{platform}"
+                    ),
+                    Layout::RawBinary,
+                ))
+            }
+            Self::Pagefind => {
+                let target = rust_target_triple(os, arch)?;
+                Ok((
+                    format!(
+                        "https://github.com/CloudCannon/pagefind/releases/download/v{version}/pagefind-v{version}-{target}.tar.gz"
+                    ),
+                    Layout::TarGz { entry_name: "pagefind" },
+                ))
+            }
+            Self::Esbuild => {
+                let package = esbuild_npm_platform(os, arch)?;
+                Ok((
+                    format!("https://registry.npmjs.org/@esbuild/{package}/-/{package}-{version}.tgz"),
+                    Layout::TarGz {
+                        entry_name: "package/bin/esbuild",
+                    },
+                ))
+            }
+        }
+    }
+}
+
+fn dart_sass_platform(os: &str, arch: &str) -> Result<&'static str> {
+    match (os, arch) {
+        ("linux", "x86_64") => Ok("linux-x64"),
+        ("linux", "aarch64") => Ok("linux-arm64"),
+        ("macos", "x86_64") => Ok("macos-x64"),
+        ("macos", "aarch64") => Ok("macos-arm64"),
+        _ => bail!("sass has no published release for {os}-{arch}"),
+    }
+}
+
+fn tailwindcss_platform(os: &str, arch: &str) -> Result<&'static str> {
+    match (os, arch) {
+        ("linux", "x86_64") => Ok("linux-x64"),
+        ("linux", "aarch64") => Ok("linux-arm64"),
+        ("macos", "x86_64") => Ok("macos-x64"),
+        ("macos", "aarch64") => Ok("macos-arm64"),
+        _ => bail!("tailwindcss has no published release for {os}-{arch}"),
+    }
+}
+
+fn rust_target_triple(os: &str, arch: &str) -> Result<&'static str> {
+    match (os, arch) {
+        ("linux", "x86_64") => Ok("x86_64-unknown-linux-musl"),
+        ("linux", "aarch64") => Ok("aarch64-unknown-linux-musl"),
+        ("macos", "x86_64") => Ok("x86_64-apple-darwin"),
+        ("macos", "aarch64") => Ok("aarch64-apple-darwin"),
+        _ => bail!("pagefind has no published release for {os}-{arch}"),
+    }
+}
+
+fn esbuild_npm_platform(os: &str, arch: &str) -> Result<&'static str> {
+    match (os, arch) {
+        ("linux", "x86_64") => Ok("linux-x64"),
+        ("linux", "aarch64") => Ok("linux-arm64"),
+        ("macos", "x86_64") => Ok("darwin-x64"),
+        ("macos", "aarch64") => Ok("darwin-arm64"),
+        _ => bail!("esbuild has no published package for {os}-{arch}"),
+    }
+}
+
+/// Resolve the path to `app`'s binary, pinned to `version` (or [`Application::default_version`]
+/// if `None`), downloading it into Trunk's tool cache first if it isn't already present there.
+///
+/// `offline` disables the download step, so only an already-cached binary can satisfy the lookup.
+pub async fn get(app: Application, version: Option<&str>, offline: bool) -> Result<PathBuf> {
+    let version = version.unwrap_or_else(|| app.default_version());
+    let cache_dir = tool_cache_dir()?.join(app.name()).join(version);
+    let bin_path = cache_dir.join(app.name());
+
+    if fs::metadata(&bin_path).await.is_ok() {
+        return Ok(bin_path);
+    }
+    if offline {
+        bail!(
+            "`{}` {version} is not in Trunk's tool cache and `--offline` is set; run a build \
+             online once to cache it",
+            app.name()
+        );
+    }
+
+    download_and_cache(app, version, &cache_dir, &bin_path)
+        .await
+        .with_context(|| format!("error downloading/caching {} {version}", app.name()))?;
+    Ok(bin_path)
+}
+
+/// The directory Trunk caches downloaded tool binaries under, `<user cache dir>/trunk/tools`.
+fn tool_cache_dir() -> Result<PathBuf> {
+    let base = dirs::cache_dir().context("could not determine the user's cache directory")?;
+    Ok(base.join("trunk").join("tools"))
+}
+
+async fn download_and_cache(
+    app: Application,
+    version: &str,
+    cache_dir: &Path,
+    bin_path: &Path,
+) -> Result<()> {
+    let (url, layout) = app.download(version)?;
+    let bytes = reqwest::get(&url)
+        .await
+        .with_context(|| format!("error requesting {url}"))?
+        .error_for_status()
+        .with_context(|| format!("{url} returned a non-success status"))?
+        .bytes()
+        .await
+        .with_context(|| format!("error reading response body from {url}"))?;
+
+    let binary = match layout {
+        Layout::RawBinary => bytes.to_vec(),
+        Layout::TarGz { entry_name } => extract_tar_gz_entry(&bytes, entry_name)?,
+    };
+
+    fs::create_dir_all(cache_dir)
+        .await
+        .with_context(|| format!("error creating tool cache dir {cache_dir:?}"))?;
+    fs::write(bin_path, binary)
+        .await
+        .with_context(|| format!("error writing cached tool binary {bin_path:?}"))?;
+    mark_executable(bin_path).await?;
+    Ok(())
+}
+
+/// Extract `entry_name` from a `.tar.gz` archive's bytes.
+fn extract_tar_gz_entry(archive_bytes: &[u8], entry_name: &str) -> Result<Vec<u8>> {
+    let mut archive = Archive::new(GzDecoder::new(Cursor::new(archive_bytes)));
+    for entry in archive
+        .entries()
+        .context("error reading tar archive entries")?
+    {
+        let mut entry = entry.context("error reading a tar archive entry")?;
+        let path = entry.path().context("error reading a tar entry's path")?;
+        if path == Path::new(entry_name) {
+            let mut buf = Vec::new();
+            std::io::copy(&mut entry, &mut buf).context("error reading archive entry contents")?;
+            return Ok(buf);
+        }
+    }
+    bail!("archive did not contain the expected entry {entry_name:?}")
+}
+
+#[cfg(unix)]
+async fn mark_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut perms = fs::metadata(path)
+        .await
+        .with_context(|| format!("error reading metadata for {path:?}"))?
+        .permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(path, perms)
+        .await
+        .with_context(|| format!("error marking {path:?} executable"))
+}
+
+#[cfg(not(unix))]
+async fn mark_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}